@@ -0,0 +1,180 @@
+//! Tower middleware that verifies a Github webhook signature once for a
+//! whole route subtree, rather than per-handler.
+
+use crate::{verify_signature_bytes, GithubEventRejection, GithubToken};
+use axum::body::{to_bytes, Body, Bytes};
+use axum::extract::Request;
+use axum::response::{IntoResponse, Response};
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// Default maximum buffered body size, matching axum's `DefaultBodyLimit`.
+const DEFAULT_MAX_BODY_SIZE: usize = 2 * 1024 * 1024;
+
+/// Layer that verifies the webhook signature against the buffered request
+/// body before handing the request to the inner service.
+///
+/// On success, the verified body bytes are inserted into the request
+/// extensions so downstream extractors (or handlers) can read the body
+/// without re-hashing it. On failure, it short-circuits with a `400`
+/// response, same as [`GithubEvent`][crate::GithubEvent].
+///
+/// The body is buffered up to `max_body_size` (2 MiB by default, same as
+/// axum's `DefaultBodyLimit`) before verification; use
+/// [`max_body_size`][Self::max_body_size] to override it.
+#[derive(Debug, Clone)]
+pub struct GithubWebhookLayer {
+    token: GithubToken,
+    max_body_size: usize,
+}
+
+impl GithubWebhookLayer {
+    pub fn new(token: GithubToken) -> Self {
+        Self {
+            token,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+        }
+    }
+
+    /// Override the maximum buffered body size.
+    #[must_use]
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+}
+
+impl<S> Layer<S> for GithubWebhookLayer {
+    type Service = GithubWebhookService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GithubWebhookService {
+            inner,
+            token: self.token.clone(),
+            max_body_size: self.max_body_size,
+        }
+    }
+}
+
+/// [`Service`] produced by [`GithubWebhookLayer`].
+#[derive(Debug, Clone)]
+pub struct GithubWebhookService<S> {
+    inner: S,
+    token: GithubToken,
+    max_body_size: usize,
+}
+
+impl<S> Service<Request> for GithubWebhookService<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let token = self.token.clone();
+        let max_body_size = self.max_body_size;
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move {
+            let (parts, body) = req.into_parts();
+            let bytes: Bytes = match to_bytes(body, max_body_size).await {
+                Ok(bytes) => bytes,
+                Err(_) => return Ok(GithubEventRejection::BodyRead.into_response()),
+            };
+            if let Err(rejection) = verify_signature_bytes(&parts.headers, &bytes, &token) {
+                return Ok(rejection.into_response());
+            }
+            let mut req = Request::from_parts(parts, Body::from(bytes.clone()));
+            req.extensions_mut().insert(bytes);
+            inner.call(req).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GithubWebhookLayer;
+    use crate::GithubToken;
+    use axum::body::{Body, Bytes};
+    use axum::extract::{Extension, Request};
+    use axum::http::StatusCode;
+    use axum::response::IntoResponse;
+    use axum::routing::post;
+    use axum::Router;
+    use http_body_util::BodyExt;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    async fn echo(Extension(body): Extension<Bytes>) -> impl IntoResponse {
+        String::from_utf8_lossy(&body).into_owned()
+    }
+
+    fn app() -> Router {
+        Router::new()
+            .route("/", post(echo))
+            .layer(GithubWebhookLayer::new(GithubToken(Arc::new(vec![
+                String::from("42"),
+            ]))))
+    }
+
+    async fn body_string(body: Body) -> String {
+        String::from_utf8_lossy(&body.collect().await.unwrap().to_bytes()).into()
+    }
+
+    #[tokio::test]
+    async fn signature_missing() {
+        let req = Request::builder()
+            .method("POST")
+            .body(Body::empty())
+            .unwrap();
+        let res = app().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(body_string(res.into_body()).await, "signature missing");
+    }
+
+    #[tokio::test]
+    async fn signature_valid() {
+        let req: Request = Request::builder()
+            .method("POST")
+            .header(
+                "X-Hub-Signature-256",
+                "sha256=8b99afd7996c3e3c291a0b54399bacb72016bdb088071de42d1d7156a6a4273d",
+            )
+            .body(r#"{"action":"hello world"}"#.into())
+            .unwrap();
+        let res = app().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            body_string(res.into_body()).await,
+            r#"{"action":"hello world"}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn body_too_large_is_rejected() {
+        let app = Router::new().route("/", post(echo)).layer(
+            GithubWebhookLayer::new(GithubToken(Arc::new(vec![String::from("42")])))
+                .max_body_size(4),
+        );
+        let req: Request = Request::builder()
+            .method("POST")
+            .header(
+                "X-Hub-Signature-256",
+                "sha256=8b99afd7996c3e3c291a0b54399bacb72016bdb088071de42d1d7156a6a4273d",
+            )
+            .body(r#"{"action":"hello world"}"#.into())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(body_string(res.into_body()).await, "error reading body");
+    }
+}
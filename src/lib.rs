@@ -27,7 +27,7 @@
 //!     let token = String::from("d4705034dd0777ee9e1e3078a12a06985151b76f");
 //!     Router::new()
 //!         .route("/", post(echo))
-//!         .with_state(GithubToken(Arc::new(token)))
+//!         .with_state(GithubToken(Arc::new(vec![token])))
 //! }
 //! ```
 //!
@@ -35,6 +35,22 @@
 //! The event payload is under your control, just make sure to configure it to
 //! use [JSON][github-json].
 //!
+//! GitHub signs every delivery with `X-Hub-Signature-256`, and additionally
+//! with the legacy `X-Hub-Signature` (HMAC-SHA1) for senders that haven't
+//! migrated yet. Enable the `sha1` feature to fall back to verifying that
+//! header when `X-Hub-Signature-256` is absent.
+//!
+//! If you need to tell events apart, use [`GithubEventTyped`] instead, which
+//! also reads the `X-GitHub-Event` header.
+//!
+//! If you'd rather verify the signature once for a whole route subtree
+//! instead of per-handler, use [`GithubWebhookLayer`] and pull the verified
+//! body out of the request extensions.
+//!
+//! To deduplicate redeliveries or correlate a request across logs, use
+//! [`GithubEventMeta`] to get the payload alongside a [`DeliveryMeta`]
+//! (delivery id, event name, and hook id).
+//!
 //! [github-webhooks]: https://docs.github.com/en/webhooks-and-events/webhooks/securing-your-webhooks
 //! [axum]: https://docs.rs/axum/latest/axum/
 //! [axum-extractor]: https://docs.rs/axum/latest/axum/#extractors
@@ -44,25 +60,133 @@
 
 use axum::body::Bytes;
 use axum::extract::{FromRef, FromRequest, Request};
-use axum::http::StatusCode;
+#[cfg(feature = "sha1")]
+use hmac::{Hmac, Mac};
 use hmac_sha256::HMAC;
 use serde::de::DeserializeOwned;
-use std::fmt::Display;
+#[cfg(feature = "sha1")]
+use sha1::Sha1;
 use std::sync::Arc;
 use subtle::ConstantTimeEq;
 
-/// State to provide the Github Token to verify Event signature.
+mod layer;
+mod rejection;
+pub use layer::{GithubWebhookLayer, GithubWebhookService};
+pub use rejection::GithubEventRejection;
+
+/// State to provide the Github Token(s) to verify Event signature.
+///
+/// Accepting more than one token makes zero-downtime secret rotation
+/// possible: configure both the old and new secret during the rotation
+/// window, and a request is accepted if it matches any of them.
 #[derive(Debug, Clone)]
-pub struct GithubToken(pub Arc<String>);
+pub struct GithubToken(pub Arc<Vec<String>>);
 
 /// Verify and extract Github Event Payload.
 #[derive(Debug, Clone, Copy, Default)]
 #[must_use]
 pub struct GithubEvent<T>(pub T);
 
-fn err(m: impl Display) -> (StatusCode, String) {
-    tracing::error!("{m}");
-    (StatusCode::BAD_REQUEST, m.to_string())
+/// Verify and extract Github Event Payload, along with the event name from
+/// the `X-GitHub-Event` header.
+///
+/// The body is wrapped as `{ "<event name>": <body> }` before it is
+/// deserialized into `T`, so `T` is typically an externally tagged enum whose
+/// variant names match Github's event names (e.g. `push`, `pull_request`),
+/// and callers can `match` on a single type instead of inspecting the
+/// payload. A plain payload struct, as used with [`GithubEvent`], does not
+/// deserialize from that shape. The event name is returned alongside it,
+/// since the header is read either way.
+#[derive(Debug, Clone, Default)]
+#[must_use]
+pub struct GithubEventTyped<T>(pub String, pub T);
+
+/// Metadata about a single webhook delivery, parsed from Github's delivery
+/// headers.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeliveryMeta {
+    /// The `X-GitHub-Delivery` header: a UUID unique to this delivery.
+    /// Github reuses it across redeliveries of the same event, so it's
+    /// useful for deduplication.
+    pub id: String,
+    /// The `X-GitHub-Event` header (e.g. `push`, `pull_request`).
+    pub event: String,
+    /// The `X-GitHub-Hook-ID` header: the id of the webhook configuration
+    /// that sent this delivery. Absent for some delivery sources.
+    pub hook_id: Option<u64>,
+}
+
+/// Verify and extract a Github Event Payload alongside its [`DeliveryMeta`].
+#[derive(Debug, Clone, Default)]
+#[must_use]
+pub struct GithubEventMeta<T>(pub DeliveryMeta, pub T);
+
+/// Verify `body` against the signature headers on `headers`, using `token`.
+///
+/// Shared by the [`FromRequest`] extractors and [`layer::GithubWebhookLayer`].
+pub(crate) fn verify_signature_bytes(
+    headers: &axum::http::HeaderMap,
+    body: &[u8],
+    token: &GithubToken,
+) -> Result<(), GithubEventRejection> {
+    let signature_sha256 = headers
+        .get("X-Hub-Signature-256")
+        .and_then(|v| v.to_str().ok());
+    #[cfg(feature = "sha1")]
+    let signature_sha1 = headers.get("X-Hub-Signature").and_then(|v| v.to_str().ok());
+
+    if let Some(signature_sha256) = signature_sha256 {
+        let signature_sha256 = signature_sha256
+            .strip_prefix("sha256=")
+            .ok_or(GithubEventRejection::SignaturePrefixMissing)?;
+        let signature =
+            hex::decode(signature_sha256).map_err(|_| GithubEventRejection::SignatureMalformed)?;
+        let matches_any = token.0.iter().any(|candidate| {
+            let mac = HMAC::mac(body, candidate.as_bytes());
+            !bool::from(mac.ct_ne(&signature))
+        });
+        if !matches_any {
+            return Err(GithubEventRejection::SignatureMismatch);
+        }
+    } else {
+        #[cfg(feature = "sha1")]
+        {
+            let signature_sha1 =
+                signature_sha1.ok_or(GithubEventRejection::SignatureMissing)?;
+            let signature_sha1 = signature_sha1
+                .strip_prefix("sha1=")
+                .ok_or(GithubEventRejection::SignaturePrefixMissing)?;
+            let signature = hex::decode(signature_sha1)
+                .map_err(|_| GithubEventRejection::SignatureMalformed)?;
+            let matches_any = token.0.iter().any(|candidate| {
+                let mut mac = Hmac::<Sha1>::new_from_slice(candidate.as_bytes())
+                    .expect("HMAC can take key of any size");
+                mac.update(body);
+                mac.verify_slice(&signature).is_ok()
+            });
+            if !matches_any {
+                return Err(GithubEventRejection::SignatureMismatch);
+            }
+        }
+        #[cfg(not(feature = "sha1"))]
+        return Err(GithubEventRejection::SignatureMissing);
+    }
+
+    Ok(())
+}
+
+async fn verify_signature<S>(req: Request, state: &S) -> Result<Bytes, GithubEventRejection>
+where
+    GithubToken: FromRef<S>,
+    S: Send + Sync,
+{
+    let token = GithubToken::from_ref(state);
+    let headers = req.headers().clone();
+    let body = Bytes::from_request(req, state)
+        .await
+        .map_err(|_| GithubEventRejection::BodyRead)?;
+    verify_signature_bytes(&headers, &body, &token)?;
+    Ok(body)
 }
 
 impl<T, S> FromRequest<S> for GithubEvent<T>
@@ -71,33 +195,88 @@ where
     T: DeserializeOwned,
     S: Send + Sync,
 {
-    type Rejection = (StatusCode, String);
+    type Rejection = GithubEventRejection;
+
+    fn from_request(
+        req: Request,
+        state: &S,
+    ) -> impl std::future::Future<Output = Result<Self, Self::Rejection>> + Send {
+        async {
+            let body = verify_signature(req, state).await?;
+            let deserializer = &mut serde_json::Deserializer::from_slice(&body);
+            let value = serde_path_to_error::deserialize(deserializer)
+                .map_err(GithubEventRejection::Deserialize)?;
+            Ok(GithubEvent(value))
+        }
+    }
+}
+
+impl<T, S> FromRequest<S> for GithubEventTyped<T>
+where
+    GithubToken: FromRef<S>,
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = GithubEventRejection;
 
     fn from_request(
         req: Request,
         state: &S,
     ) -> impl std::future::Future<Output = Result<Self, Self::Rejection>> + Send {
         async {
-            let token = GithubToken::from_ref(state);
-            let signature_sha256 = req
+            let event = req
                 .headers()
-                .get("X-Hub-Signature-256")
+                .get("X-GitHub-Event")
                 .and_then(|v| v.to_str().ok())
-                .ok_or_else(|| err("signature missing"))?
-                .strip_prefix("sha256=")
-                .ok_or_else(|| err("signature prefix missing"))?;
-            let signature =
-                hex::decode(signature_sha256).map_err(|_| err("signature malformed"))?;
-            let body = Bytes::from_request(req, state)
-                .await
-                .map_err(|_| err("error reading body"))?;
-            let mac = HMAC::mac(&body, token.0.as_bytes());
-            if mac.ct_ne(&signature).into() {
-                return Err(err("signature mismatch"));
-            }
+                .map(str::to_owned)
+                .ok_or(GithubEventRejection::EventTypeMissing)?;
+            let body = verify_signature(req, state).await?;
+            let value: serde_json::Value =
+                serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_slice(&body))
+                    .map_err(GithubEventRejection::Deserialize)?;
+            let tagged = serde_json::json!({ &event: value });
+            let payload = serde_path_to_error::deserialize(&tagged)
+                .map_err(GithubEventRejection::Deserialize)?;
+            Ok(GithubEventTyped(event, payload))
+        }
+    }
+}
+
+impl<T, S> FromRequest<S> for GithubEventMeta<T>
+where
+    GithubToken: FromRef<S>,
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = GithubEventRejection;
+
+    fn from_request(
+        req: Request,
+        state: &S,
+    ) -> impl std::future::Future<Output = Result<Self, Self::Rejection>> + Send {
+        async {
+            let headers = req.headers();
+            let id = headers
+                .get("X-GitHub-Delivery")
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_owned)
+                .ok_or(GithubEventRejection::DeliveryIdMissing)?;
+            let event = headers
+                .get("X-GitHub-Event")
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_owned)
+                .ok_or(GithubEventRejection::EventTypeMissing)?;
+            let hook_id = headers
+                .get("X-GitHub-Hook-ID")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok());
+            let meta = DeliveryMeta { id, event, hook_id };
+
+            let body = verify_signature(req, state).await?;
             let deserializer = &mut serde_json::Deserializer::from_slice(&body);
-            let value = serde_path_to_error::deserialize(deserializer).map_err(err)?;
-            Ok(GithubEvent(value))
+            let value = serde_path_to_error::deserialize(deserializer)
+                .map_err(GithubEventRejection::Deserialize)?;
+            Ok(GithubEventMeta(meta, value))
         }
     }
 }
@@ -115,7 +294,7 @@ mod tests {
     use std::sync::Arc;
     use tower::ServiceExt;
 
-    use super::{GithubEvent, GithubToken};
+    use super::{GithubEvent, GithubEventMeta, GithubEventTyped, GithubToken};
 
     #[derive(Debug, Deserialize)]
     struct Event {
@@ -129,7 +308,7 @@ mod tests {
     fn app() -> Router {
         Router::new()
             .route("/", post(echo))
-            .with_state(GithubToken(Arc::new(String::from("42"))))
+            .with_state(GithubToken(Arc::new(vec![String::from("42")])))
     }
 
     async fn body_string(body: Body) -> String {
@@ -193,4 +372,162 @@ mod tests {
         assert_eq!(res.status(), StatusCode::OK);
         assert_eq!(body_string(res.into_body()).await, "hello world");
     }
+
+    #[cfg(feature = "sha1")]
+    #[tokio::test]
+    async fn signature_sha1_valid() {
+        let req: Request = Request::builder()
+            .method("POST")
+            .header("X-Hub-Signature", "sha1=b34aa4c0990be3a0c910a6c1feb39fffc0a04443")
+            .body(r#"{"action":"hello world"}"#.into())
+            .unwrap();
+        let res = app().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(body_string(res.into_body()).await, "hello world");
+    }
+
+    #[cfg(feature = "sha1")]
+    #[tokio::test]
+    async fn signature_sha1_mismatch() {
+        let req: Request = Request::builder()
+            .method("POST")
+            .header("X-Hub-Signature", "sha1=0000000000000000000000000000000000000000")
+            .body(r#"{"action":"hello world"}"#.into())
+            .unwrap();
+        let res = app().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(body_string(res.into_body()).await, "signature mismatch");
+    }
+
+    fn app_rotating() -> Router {
+        Router::new()
+            .route("/", post(echo))
+            .with_state(GithubToken(Arc::new(vec![
+                String::from("42"),
+                String::from("43"),
+            ])))
+    }
+
+    #[tokio::test]
+    async fn signature_valid_with_new_token_during_rotation() {
+        let req: Request = Request::builder()
+            .method("POST")
+            .header(
+                "X-Hub-Signature-256",
+                "sha256=1ce5982934a7be763cd350eac5c18185c28040a1d65d0ff0cdbd56a495433d44",
+            )
+            .body(r#"{"action":"hello world"}"#.into())
+            .unwrap();
+        let res = app_rotating().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(body_string(res.into_body()).await, "hello world");
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(non_camel_case_types)]
+    enum WebhookEvent {
+        push(PushEvent),
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct PushEvent {
+        r#ref: String,
+    }
+
+    async fn echo_typed(
+        GithubEventTyped(event, payload): GithubEventTyped<WebhookEvent>,
+    ) -> impl IntoResponse {
+        match payload {
+            WebhookEvent::push(p) => format!("{event}:{}", p.r#ref),
+        }
+    }
+
+    fn app_typed() -> Router {
+        Router::new()
+            .route("/", post(echo_typed))
+            .with_state(GithubToken(Arc::new(vec![String::from("42")])))
+    }
+
+    #[tokio::test]
+    async fn typed_event_missing() {
+        let req: Request = Request::builder()
+            .method("POST")
+            .header(
+                "X-Hub-Signature-256",
+                "sha256=c65ee1100fdb2bfff5df1e5e215b8ea368e359a030cbfdc85ece8e2008a90560",
+            )
+            .body(r#"{"ref":"refs/heads/main"}"#.into())
+            .unwrap();
+        let res = app_typed().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(body_string(res.into_body()).await, "event type missing");
+    }
+
+    #[tokio::test]
+    async fn typed_event_valid() {
+        let req: Request = Request::builder()
+            .method("POST")
+            .header("X-GitHub-Event", "push")
+            .header(
+                "X-Hub-Signature-256",
+                "sha256=c65ee1100fdb2bfff5df1e5e215b8ea368e359a030cbfdc85ece8e2008a90560",
+            )
+            .body(r#"{"ref":"refs/heads/main"}"#.into())
+            .unwrap();
+        let res = app_typed().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            body_string(res.into_body()).await,
+            "push:refs/heads/main"
+        );
+    }
+
+    async fn echo_meta(
+        GithubEventMeta(meta, e): GithubEventMeta<Event>,
+    ) -> impl IntoResponse {
+        format!("{}:{}:{:?}:{}", meta.id, meta.event, meta.hook_id, e.action)
+    }
+
+    fn app_meta() -> Router {
+        Router::new()
+            .route("/", post(echo_meta))
+            .with_state(GithubToken(Arc::new(vec![String::from("42")])))
+    }
+
+    #[tokio::test]
+    async fn meta_delivery_id_missing() {
+        let req: Request = Request::builder()
+            .method("POST")
+            .header("X-GitHub-Event", "push")
+            .header(
+                "X-Hub-Signature-256",
+                "sha256=8b99afd7996c3e3c291a0b54399bacb72016bdb088071de42d1d7156a6a4273d",
+            )
+            .body(r#"{"action":"hello world"}"#.into())
+            .unwrap();
+        let res = app_meta().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(body_string(res.into_body()).await, "delivery id missing");
+    }
+
+    #[tokio::test]
+    async fn meta_valid() {
+        let req: Request = Request::builder()
+            .method("POST")
+            .header("X-GitHub-Delivery", "72d3162e-cc78-11e3-81ab-4c9367dc0958")
+            .header("X-GitHub-Event", "push")
+            .header("X-GitHub-Hook-ID", "12345")
+            .header(
+                "X-Hub-Signature-256",
+                "sha256=8b99afd7996c3e3c291a0b54399bacb72016bdb088071de42d1d7156a6a4273d",
+            )
+            .body(r#"{"action":"hello world"}"#.into())
+            .unwrap();
+        let res = app_meta().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            body_string(res.into_body()).await,
+            "72d3162e-cc78-11e3-81ab-4c9367dc0958:push:Some(12345):hello world"
+        );
+    }
 }
@@ -0,0 +1,66 @@
+//! Structured rejection returned by this crate's extractors and middleware.
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use std::fmt;
+
+/// Why a Github webhook request was rejected.
+///
+/// Every variant renders as a `400 Bad Request` whose body is its `Display`
+/// message. The signature, body and deserialization failures keep the
+/// messages this crate has always used, so existing callers relying on the
+/// response body see no change. Match on it to log selectively or build your
+/// own response.
+#[derive(Debug)]
+pub enum GithubEventRejection {
+    /// Neither `X-Hub-Signature-256`, nor (with the `sha1` feature)
+    /// `X-Hub-Signature`, was present.
+    SignatureMissing,
+    /// The signature header was present but missing its `sha256=`/`sha1=`
+    /// prefix.
+    SignaturePrefixMissing,
+    /// The signature header's hex payload could not be decoded.
+    SignatureMalformed,
+    /// The signature didn't match any configured [`GithubToken`][crate::GithubToken].
+    SignatureMismatch,
+    /// The `X-GitHub-Event` header was missing.
+    EventTypeMissing,
+    /// The `X-GitHub-Delivery` header was missing.
+    DeliveryIdMissing,
+    /// The request body could not be read.
+    BodyRead,
+    /// The body was read and signature-verified, but didn't deserialize
+    /// into the target type.
+    Deserialize(serde_path_to_error::Error<serde_json::Error>),
+}
+
+impl fmt::Display for GithubEventRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SignatureMissing => f.write_str("signature missing"),
+            Self::SignaturePrefixMissing => f.write_str("signature prefix missing"),
+            Self::SignatureMalformed => f.write_str("signature malformed"),
+            Self::SignatureMismatch => f.write_str("signature mismatch"),
+            Self::EventTypeMissing => f.write_str("event type missing"),
+            Self::DeliveryIdMissing => f.write_str("delivery id missing"),
+            Self::BodyRead => f.write_str("error reading body"),
+            Self::Deserialize(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for GithubEventRejection {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Deserialize(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl IntoResponse for GithubEventRejection {
+    fn into_response(self) -> Response {
+        tracing::error!("{self}");
+        (StatusCode::BAD_REQUEST, self.to_string()).into_response()
+    }
+}